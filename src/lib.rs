@@ -1,73 +1,385 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 
-use lopdf::{dictionary, Document, Object, ObjectId};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream, StringFormat};
 use std::path::Path;
 
+/// Errors that can occur while reading, merging, or writing a pdf.
+#[derive(Debug)]
+pub enum MergeError {
+    /// The underlying pdf could not be parsed or manipulated.
+    Pdf(lopdf::Error),
+    /// A pdf could not be read from or written to disk, or could not be encoded.
+    Io(std::io::Error),
+    /// The document's `/Pages` root is missing.
+    PagesRootNotFound,
+    /// The document's `/Catalog` root is missing.
+    CatalogRootNotFound,
+    /// A [`PageSource`] selected a page index that its document doesn't have.
+    PageIndexOutOfRange(u32),
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::Pdf(err) => write!(f, "pdf error: {err}"),
+            MergeError::Io(err) => write!(f, "io error: {err}"),
+            MergeError::PagesRootNotFound => write!(f, "Pages root not found"),
+            MergeError::CatalogRootNotFound => write!(f, "Catalog root not found"),
+            MergeError::PageIndexOutOfRange(index) => {
+                write!(f, "page index {index} is out of range for its source document")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+impl From<lopdf::Error> for MergeError {
+    fn from(err: lopdf::Error) -> Self {
+        MergeError::Pdf(err)
+    }
+}
+
+impl From<std::io::Error> for MergeError {
+    fn from(err: std::io::Error) -> Self {
+        MergeError::Io(err)
+    }
+}
+
+/// Get the number of pages from a pdf already loaded into memory.
+///
+/// # Errors
+/// Returns [`MergeError::Pdf`] if `pdf` is not a valid pdf.
+pub fn pdf_get_size_bytes(pdf: &[u8]) -> Result<usize, MergeError> {
+    let document = Document::load_mem(pdf)?;
+    Ok(document.page_iter().count())
+}
+
 /// Get the number of pages from a pdf.
 ///
-/// # Panic
-/// This function will panic if the pdf does not exist or otherwise cannot be opened.
-pub fn pdf_get_size(pdf: &Path) -> usize {
-    let document = Document::load(pdf);
-    let document = document.unwrap();
-    document.page_iter().count()
+/// # Errors
+/// Returns [`MergeError::Io`] if the file cannot be read, and [`MergeError::Pdf`] if it is not a
+/// valid pdf.
+pub fn pdf_get_size(pdf: &Path) -> Result<usize, MergeError> {
+    pdf_get_size_bytes(&std::fs::read(pdf)?)
+}
+
+/// Look up `key` on `object_id`, walking up `/Parent` links if it's not set directly. Used for
+/// page attributes like `/MediaBox` and `/Resources` that the pdf spec allows a page to inherit
+/// from its ancestor `/Pages` nodes instead of setting itself.
+fn inherited_attribute(document: &Document, mut object_id: ObjectId, key: &[u8]) -> Option<Object> {
+    loop {
+        let dict = document.get_object(object_id).ok()?.as_dict().ok()?;
+        if let Ok(value) = dict.get(key) {
+            return Some(value.clone());
+        }
+        object_id = dict.get(b"Parent").ok()?.as_reference().ok()?;
+    }
 }
 
 /// Make sure that the pdf has a even number of pages. This may be desirable if a pdf is merged that
 /// should be double-sided printed.
 ///
-/// # Panic
-/// This function will panic if the pdf does not exist or otherwise cannot be opened.
-/// This function will panic if the pdf has an uexpectred structure
-pub fn make_page_count_even(pdf: &Path) {
-    let mut document = Document::load(pdf).unwrap();
+/// # Errors
+/// Returns [`MergeError::Pdf`] if `pdf` is not a valid pdf or has an unexpected structure, and
+/// [`MergeError::PagesRootNotFound`] if its catalog's `/Pages` is not an indirect reference.
+pub fn make_page_count_even_bytes(pdf: &[u8]) -> Result<Vec<u8>, MergeError> {
+    let mut document = Document::load_mem(pdf)?;
     let document_length = document.get_pages().len() as u32;
 
     if document_length % 2 != 0 {
-        let catalog = document.catalog().unwrap();
-        let pages_id_ref = catalog.get(b"Pages").unwrap();
-        let (pages_id, _) = document.dereference(pages_id_ref).unwrap();
-        let pages_id = pages_id.unwrap();
+        let catalog = document.catalog()?;
+        let pages_id_ref = catalog.get(b"Pages")?;
+        let (pages_id, _) = document.dereference(pages_id_ref)?;
+        let pages_id = pages_id.ok_or(MergeError::PagesRootNotFound)?;
+
+        // Match the last existing page's physical size (and resources) so the filler page
+        // doesn't render at a mismatched default size, e.g. when double-sided printing.
+        let reference_page_id = document.get_pages().values().next_back().copied().unwrap_or(pages_id);
+        let media_box = inherited_attribute(&document, reference_page_id, b"MediaBox");
+        let resources = inherited_attribute(&document, reference_page_id, b"Resources");
 
         // Create and add a new empty page
-        let page = dictionary! {
+        let mut page = dictionary! {
             "Type" => "Page",
             "Parent" => pages_id,
         };
+        if let Some(media_box) = media_box {
+            page.set("MediaBox", media_box);
+        }
+        if let Some(resources) = resources {
+            page.set("Resources", resources);
+        }
         let page_id = document.add_object(page);
 
         // add the new page to the pages and update the page count
-        let pages = document.get_object_mut(pages_id).unwrap();
-        let pages = pages.as_dict_mut().unwrap();
+        let pages = document.get_object_mut(pages_id)?;
+        let pages = pages.as_dict_mut()?;
 
-        let pages_kids = pages.get_mut(b"Kids").unwrap();
-        let pages_kids = pages_kids.as_array_mut()
-            .unwrap();
+        let pages_kids = pages.get_mut(b"Kids")?;
+        let pages_kids = pages_kids.as_array_mut()?;
         pages_kids.push(Object::Reference(page_id));
 
-        let pages_count = pages.get_mut(b"Count").unwrap();
-        let new_count = pages_count.as_i64().unwrap() + 1;
+        let pages_count = pages.get_mut(b"Count")?;
+        let new_count = pages_count.as_i64()? + 1;
         pages.set("Count", new_count as i64);
 
         document.renumber_objects();
-        document.save(pdf).unwrap();
     }
+
+    let mut buffer = Vec::new();
+    document.save_to(&mut buffer)?;
+    Ok(buffer)
 }
 
-/// Insert a pdf `source` in possible multiple places in another pdf `destination`.
+/// Make sure that the pdf has a even number of pages. This may be desirable if a pdf is merged that
+/// should be double-sided printed.
 ///
-/// # NOTE:
-/// This overwrites the pdf `destination`.
+/// # Errors
+/// Returns [`MergeError::Io`] if the pdf cannot be read or the updated pdf cannot be saved,
+/// [`MergeError::Pdf`] if it is not a valid pdf or has an unexpected structure, and
+/// [`MergeError::PagesRootNotFound`] if its catalog's `/Pages` is not an indirect reference.
+pub fn make_page_count_even(pdf: &Path) -> Result<(), MergeError> {
+    let updated = make_page_count_even_bytes(&std::fs::read(pdf)?)?;
+    std::fs::write(pdf, updated)?;
+    Ok(())
+}
+
+/// Where on the page, derived from its `/MediaBox`, [`stamp_page_numbers`] draws its text.
+#[derive(Debug, Clone, Copy)]
+pub enum StampPosition {
+    BottomCenter,
+    BottomRight,
+}
+
+/// Options for [`stamp_page_numbers`].
+#[derive(Debug, Clone)]
+pub struct StampOptions {
+    /// Template for the stamped text. `{page}` and `{total}` are replaced with the current
+    /// page number and the total page count.
+    pub format: String,
+    /// Font size, in points.
+    pub font_size: f32,
+    /// Where on the page to place the text.
+    pub position: StampPosition,
+}
+
+impl Default for StampOptions {
+    fn default() -> Self {
+        StampOptions {
+            format: "Page {page} of {total}".to_string(),
+            font_size: 10.0,
+            position: StampPosition::BottomCenter,
+        }
+    }
+}
+
+/// Width and height of a `/MediaBox` array, falling back to US Letter if it's missing or
+/// malformed.
+fn media_box_dimensions(media_box: Option<&Object>) -> (f32, f32) {
+    const DEFAULT: (f32, f32) = (612.0, 792.0);
+
+    fn as_f32(object: &Object) -> Option<f32> {
+        match object {
+            Object::Integer(value) => Some(*value as f32),
+            Object::Real(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    let Some(Object::Array(corners)) = media_box else {
+        return DEFAULT;
+    };
+    match corners.as_slice() {
+        [x0, y0, x1, y1] => match (as_f32(x0), as_f32(y0), as_f32(x1), as_f32(y1)) {
+            (Some(x0), Some(y0), Some(x1), Some(y1)) => (x1 - x0, y1 - y0),
+            _ => DEFAULT,
+        },
+        _ => DEFAULT,
+    }
+}
+
+const STAMP_FONT_NAME: &[u8] = b"PdfMergerStamp";
+
+/// Overlay text (e.g. a page number or footer) onto the bottom of every page of `pdf`, as a
+/// companion step when assembling print-ready booklets out of merged documents.
 ///
-/// # Panic
-/// This panics, if
-/// - src is not a pdf or cannot be opened
-/// - dst is not a pdf or cannot be opened
-/// - the structure of the pdf is not as expected.
+/// # Errors
+/// Returns [`MergeError::Pdf`] if the pdf does not exist, cannot be opened, or has an
+/// unexpected structure, and [`MergeError::Io`] if the stamped pdf cannot be saved.
+pub fn stamp_page_numbers(pdf: &Path, options: StampOptions) -> Result<(), MergeError> {
+    let mut document = Document::load(pdf)?;
+
+    // Register the stamp font once; every page's content stream just references it.
+    let font_id = document.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Courier",
+    });
+
+    let pages = document.get_pages();
+    let total = pages.len();
+
+    for (page_number, page_id) in pages {
+        let media_box = inherited_attribute(&document, page_id, b"MediaBox");
+        let (width, height) = media_box_dimensions(media_box.as_ref());
+
+        let text = options
+            .format
+            .replace("{page}", &page_number.to_string())
+            .replace("{total}", &total.to_string());
+
+        // Courier is a fixed-pitch font: every glyph is 0.6 * font_size wide.
+        let text_width = text.len() as f32 * options.font_size * 0.6;
+        let (x, y) = match options.position {
+            StampPosition::BottomCenter => ((width - text_width) / 2.0, height * 0.03),
+            StampPosition::BottomRight => (width - text_width - 36.0, height * 0.03),
+        };
+
+        let content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new(
+                    "Tf",
+                    vec![Object::Name(STAMP_FONT_NAME.to_vec()), Object::Real(options.font_size)],
+                ),
+                Operation::new("Td", vec![Object::Real(x), Object::Real(y)]),
+                Operation::new(
+                    "Tj",
+                    vec![Object::String(text.into_bytes(), StringFormat::Literal)],
+                ),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let stream_id = document.add_object(Stream::new(dictionary! {}, content.encode()?));
+
+        let mut resources = inherited_attribute(&document, page_id, b"Resources")
+            .and_then(|object| object.as_dict().ok().cloned())
+            .unwrap_or_else(Dictionary::new);
+        let mut font_dict = resources
+            .get(b"Font")
+            .ok()
+            .and_then(|object| object.as_dict().ok().cloned())
+            .unwrap_or_else(Dictionary::new);
+        font_dict.set(STAMP_FONT_NAME, Object::Reference(font_id));
+        resources.set("Font", Object::Dictionary(font_dict));
+
+        let page_dict = document.get_object_mut(page_id)?.as_dict_mut()?;
+        page_dict.set("Resources", Object::Dictionary(resources));
+
+        match page_dict.get(b"Contents").ok().cloned() {
+            Some(Object::Array(mut contents)) => {
+                contents.push(Object::Reference(stream_id));
+                page_dict.set("Contents", Object::Array(contents));
+            }
+            Some(existing @ Object::Reference(_)) => {
+                page_dict.set(
+                    "Contents",
+                    Object::Array(vec![existing, Object::Reference(stream_id)]),
+                );
+            }
+            _ => {
+                page_dict.set("Contents", Object::Reference(stream_id));
+            }
+        }
+    }
+
+    document.save(pdf)?;
+    Ok(())
+}
+
+/// Return the top-level items (in document order) of the outline tree rooted at `parent`,
+/// following the `/First` -> `/Next` chain.
+fn outline_children(objects: &BTreeMap<ObjectId, Object>, parent: ObjectId) -> Vec<ObjectId> {
+    let mut children = Vec::new();
+
+    let mut current = objects
+        .get(&parent)
+        .and_then(|object| object.as_dict().ok())
+        .and_then(|dict| dict.get(b"First").ok())
+        .and_then(|object| object.as_reference().ok());
+
+    while let Some(id) = current {
+        children.push(id);
+        current = objects
+            .get(&id)
+            .and_then(|object| object.as_dict().ok())
+            .and_then(|dict| dict.get(b"Next").ok())
+            .and_then(|object| object.as_reference().ok());
+    }
+
+    children
+}
+
+/// Collect every outline item in the subtree below (but not including) `root`.
+fn outline_descendants(objects: &BTreeMap<ObjectId, Object>, root: ObjectId) -> Vec<ObjectId> {
+    let mut descendants = Vec::new();
+    let mut stack = outline_children(objects, root);
+
+    while let Some(id) = stack.pop() {
+        stack.extend(outline_children(objects, id));
+        descendants.push(id);
+    }
+
+    descendants
+}
+
+/// Count how many descendant outline items under `root` are visible by default. Per the pdf
+/// spec an item's own `/Count` is negative when its subtree is collapsed, in which case its
+/// descendants aren't shown (and so don't contribute to an ancestor's `/Count`) until the user
+/// expands it.
+fn outline_open_descendant_count(objects: &BTreeMap<ObjectId, Object>, root: ObjectId) -> i64 {
+    let mut count = 0;
+
+    for child in outline_children(objects, root) {
+        count += 1;
+
+        let is_closed = objects
+            .get(&child)
+            .and_then(|object| object.as_dict().ok())
+            .and_then(|dict| dict.get(b"Count").ok())
+            .and_then(|object| object.as_i64().ok())
+            .is_some_and(|value| value < 0);
+
+        if !is_closed {
+            count += outline_open_descendant_count(objects, child);
+        }
+    }
+
+    count
+}
+
+/// Remap an outline item's direct `/Dest` page reference through `page_id_map`. Destinations
+/// given as a name (looked up via the document's `/Names` tree) are left untouched, since we
+/// don't have an old-id -> new-id mapping for whatever the name tree resolves to.
+fn remap_outline_dest(dict: &mut Dictionary, page_id_map: &BTreeMap<ObjectId, ObjectId>) {
+    if let Ok(Object::Array(dest)) = dict.get(b"Dest") {
+        let mut dest = dest.clone();
+        if let Some(Object::Reference(old_page_id)) = dest.first() {
+            if let Some(new_page_id) = page_id_map.get(old_page_id) {
+                dest[0] = Object::Reference(*new_page_id);
+                dict.set("Dest", Object::Array(dest));
+            }
+        }
+    }
+}
+
+/// Insert a pdf `source` in possible multiple places in another pdf `destination`, both held
+/// in memory, and return the merged pdf's bytes.
 ///
-pub fn insert(destination: &Path, after_pages: &Vec<u32>, source: &Path) {
-    let mut document_dst = Document::load(destination).unwrap();
-    let mut document_src = Document::load(source).unwrap();
+/// # Errors
+/// Returns [`MergeError::Pdf`] if `source` or `destination` is not a pdf, [`MergeError::Io`] if
+/// the merged pdf cannot be encoded, and [`MergeError::PagesRootNotFound`] or
+/// [`MergeError::CatalogRootNotFound`] if either pdf is missing the corresponding root object.
+pub fn insert_bytes(
+    destination: &[u8],
+    after_pages: &[u32],
+    source: &[u8],
+) -> Result<Vec<u8>, MergeError> {
+    let mut document_dst = Document::load_mem(destination)?;
+    let mut document_src = Document::load_mem(source)?;
 
     // Initialize a new empty document
     let mut document = Document::with_version("1.5");
@@ -79,14 +391,20 @@ pub fn insert(destination: &Path, after_pages: &Vec<u32>, source: &Path) {
     let mut documents_pages = BTreeMap::new();
     let mut documents_objects = BTreeMap::new();
 
+    // Maps a page's id (after renumbering, but before the page gets its final position-derived
+    // id below) to the id it ends up with in the merged document. Used to remap `/Dest` entries
+    // in outline items so bookmarks keep pointing at the right page.
+    let mut page_id_map: BTreeMap<ObjectId, ObjectId> = BTreeMap::new();
+    // Maps a dst page's id (after renumbering) to its 0-based page index in dst, used to
+    // position the spliced-in source outline relative to the dst outline.
+    let mut dst_page_number: BTreeMap<ObjectId, u32> = BTreeMap::new();
+
     // Get number of pages
     let dst_num_pages = document_dst.get_pages().len() as u32;
     let src_num_pages = document_src.get_pages().len() as u32;
     let num_pages = dst_num_pages + after_pages.len() as u32 * src_num_pages;
-    println!("Will result in {} pages", num_pages);
 
     max_id = num_pages + 1;
-    //println!("max id {}", max_id);
 
     document_dst.renumber_objects_with(max_id);
 
@@ -100,19 +418,30 @@ pub fn insert(destination: &Path, after_pages: &Vec<u32>, source: &Path) {
         document_dst
             .get_pages()
             .into_iter()
-            .map(|(_, object_id)| {
+            .map(|(_, object_id)| -> Result<(ObjectId, Object), lopdf::Error> {
                 if after_pages.contains(&origin_page_count) {
                     result_page_index += src_num_pages;
                 }
                 result_page_index += 1;
                 origin_page_count += 1;
-                (
-                    (result_page_index, object_id.1),
-                    document_dst.get_object(object_id).unwrap().to_owned(),
-                )
+
+                let final_id = (result_page_index, object_id.1);
+                page_id_map.insert(object_id, final_id);
+                dst_page_number.insert(object_id, origin_page_count - 1);
+
+                Ok((final_id, document_dst.get_object(object_id)?.to_owned()))
             })
-            .collect::<BTreeMap<ObjectId, Object>>(),
+            .collect::<Result<BTreeMap<ObjectId, Object>, lopdf::Error>>()?,
     );
+
+    // The `/Outlines` root, if any, of dst's catalog. Captured before `document_dst.objects`
+    // is moved into `documents_objects` below.
+    let dst_outlines_root = document_dst
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"Outlines").ok())
+        .and_then(|object| object.as_reference().ok());
+
     documents_objects.extend(document_dst.objects);
 
     assert_eq!(dst_num_pages, origin_page_count);
@@ -121,6 +450,14 @@ pub fn insert(destination: &Path, after_pages: &Vec<u32>, source: &Path) {
     // renumber the objects to make sure that the indexes don't collide with the indexes from the other file.
     document_src.renumber_objects_with(max_id);
 
+    // The `/Outlines` root, if any, of src's catalog. Captured before `document_src.objects`
+    // is moved into `documents_objects` below.
+    let src_outlines_root = document_src
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"Outlines").ok())
+        .and_then(|object| object.as_reference().ok());
+
     // Add the pages from src with the correct indexes
     let mut added_pages = 0;
     for i in after_pages {
@@ -129,14 +466,17 @@ pub fn insert(destination: &Path, after_pages: &Vec<u32>, source: &Path) {
             document_src
                 .get_pages()
                 .into_iter()
-                .map(|(_, object_id)| {
+                .map(|(_, object_id)| -> Result<(ObjectId, Object), lopdf::Error> {
                     result_page_index += 1;
-                    (
-                        (result_page_index, object_id.1),
-                        document_src.get_object(object_id).unwrap().to_owned(),
-                    )
+
+                    let final_id = (result_page_index, object_id.1);
+                    // src is inserted once per entry in `after_pages`; a source bookmark can
+                    // only point at one of those occurrences, so we keep the first.
+                    page_id_map.entry(object_id).or_insert(final_id);
+
+                    Ok((final_id, document_src.get_object(object_id)?.to_owned()))
                 })
-                .collect::<BTreeMap<ObjectId, Object>>(),
+                .collect::<Result<BTreeMap<ObjectId, Object>, lopdf::Error>>()?,
         );
         added_pages += src_num_pages;
     }
@@ -148,10 +488,12 @@ pub fn insert(destination: &Path, after_pages: &Vec<u32>, source: &Path) {
     let mut catalog_object: Option<(ObjectId, Object)> = None;
     let mut pages_object: Option<(ObjectId, Object)> = None;
 
-    // Process all objects except "Page" type
+    // Process all objects except "Page" type. Note: we don't special-case "Outlines" here by
+    // its `/Type` tag, since that tag is optional per the pdf spec and a root without one would
+    // otherwise slip through to the catch-all below and get needlessly duplicated; the id we
+    // actually reuse for the merged root comes from `dst_outlines_root`/`src_outlines_root`
+    // (resolved via the catalog's `/Outlines` reference, not by tag-sniffing) further down.
     for (object_id, object) in documents_objects.iter() {
-        // We have to ignore "Page", "Outlines" and "Outline" objects
-        // All other objects should be collected and inserted into the main Document
         match object.type_name().unwrap_or("") {
             "Catalog" => {
                 // Collect a first "Catalog" object and use it for the future "Pages"
@@ -185,23 +527,21 @@ pub fn insert(destination: &Path, after_pages: &Vec<u32>, source: &Path) {
                     ));
                 }
             }
-            "Page" => {}     // Ignored, processed later and separately
-            "Outlines" => {
-                println!("Outlines not suppoted");
-            }
-            "Outline" => {
-                println!("Outline not suppoted");
-            }
+            "Page" => {} // Ignored, processed later and separately
             _ => {
                 document.objects.insert(*object_id, object.clone());
             }
         }
     }
 
+    // The id of the merged `/Outlines` root, if dst or src (or both) had one. Any original
+    // dict inserted under this id by the catch-all above gets overwritten once the spliced
+    // `/First`/`/Last`/`/Count` are known.
+    let outlines_object = dst_outlines_root.or(src_outlines_root);
+
     // If no "Pages" found abort
     if pages_object.is_none() {
-        println!("Pages root not found.");
-        return;
+        return Err(MergeError::PagesRootNotFound);
     }
 
     // Iter over all "Page" and collect with the parent "Pages" created before
@@ -218,13 +558,123 @@ pub fn insert(destination: &Path, after_pages: &Vec<u32>, source: &Path) {
 
     // If no "Catalog" found abort
     if catalog_object.is_none() {
-        println!("Catalog root not found.");
-        return;
+        return Err(MergeError::CatalogRootNotFound);
     }
 
     let catalog_object = catalog_object.unwrap();
     let pages_object = pages_object.unwrap();
 
+    // Merge dst's and src's outline (bookmark) trees, if either has one. Source bookmarks are
+    // spliced into dst's top-level chain right after the dst bookmarks that target one of the
+    // first `after_pages[0]` dst pages, i.e. the dst pages that precede the first inserted copy of
+    // src, so they land next to the pages they were inserted after.
+    let dst_top = dst_outlines_root
+        .map(|root| outline_children(&documents_objects, root))
+        .unwrap_or_default();
+    let src_top = src_outlines_root
+        .map(|root| outline_children(&documents_objects, root))
+        .unwrap_or_default();
+
+    // Compute the splice position from `documents_objects`, i.e. before `/Dest` gets remapped
+    // below: its page references are still dst's renumbered (not yet position-derived) page
+    // ids, which is what
+    // `dst_page_number` is keyed by. (The remap loop below rewrites these to the final,
+    // post-splice page ids in `document.objects`, which no longer match `dst_page_number`.)
+    let split_at = after_pages
+        .first()
+        .map(|first_offset| {
+            dst_top
+                .iter()
+                .filter(|item_id| {
+                    documents_objects
+                        .get(item_id)
+                        .and_then(|object| object.as_dict().ok())
+                        .and_then(|dict| dict.get(b"Dest").ok())
+                        .and_then(|dest| dest.as_array().ok())
+                        .and_then(|dest| dest.first())
+                        .and_then(|object| object.as_reference().ok())
+                        .and_then(|old_page_id| dst_page_number.get(&old_page_id))
+                        .map(|page_number| *page_number < *first_offset)
+                        .unwrap_or(false)
+                })
+                .count()
+        })
+        .unwrap_or(dst_top.len());
+
+    for item_id in dst_outlines_root
+        .iter()
+        .flat_map(|root| outline_descendants(&documents_objects, *root))
+        .chain(
+            src_outlines_root
+                .iter()
+                .flat_map(|root| outline_descendants(&documents_objects, *root)),
+        )
+    {
+        if let Some(Object::Dictionary(dict)) = document.objects.get_mut(&item_id) {
+            remap_outline_dest(dict, &page_id_map);
+        }
+    }
+
+    let merged_top: Vec<ObjectId> = if src_top.is_empty() {
+        dst_top
+    } else if dst_top.is_empty() {
+        src_top
+    } else {
+        let mut merged = dst_top[..split_at].to_vec();
+        merged.extend(src_top);
+        merged.extend(dst_top[split_at..].iter().copied());
+        merged
+    };
+
+    // Relink the merged top-level chain and recompute a single root "/Outlines" dictionary.
+    if !merged_top.is_empty() {
+        if let Some(root_id) = outlines_object {
+            for (index, item_id) in merged_top.iter().enumerate() {
+                if let Some(Object::Dictionary(dict)) = document.objects.get_mut(item_id) {
+                    dict.set("Parent", root_id);
+                    if index > 0 {
+                        dict.set("Prev", merged_top[index - 1]);
+                    } else {
+                        dict.remove(b"Prev");
+                    }
+                    if index + 1 < merged_top.len() {
+                        dict.set("Next", merged_top[index + 1]);
+                    } else {
+                        dict.remove(b"Next");
+                    }
+                }
+            }
+
+            // A closed top-level item (negative `/Count`) contributes only itself.
+            let count: i64 = merged_top
+                .iter()
+                .map(|item_id| {
+                    let is_closed = documents_objects
+                        .get(item_id)
+                        .and_then(|object| object.as_dict().ok())
+                        .and_then(|dict| dict.get(b"Count").ok())
+                        .and_then(|object| object.as_i64().ok())
+                        .is_some_and(|value| value < 0);
+                    if is_closed {
+                        1
+                    } else {
+                        1 + outline_open_descendant_count(&documents_objects, *item_id)
+                    }
+                })
+                .sum();
+
+            let outlines_dict = dictionary! {
+                "Type" => "Outlines",
+                "First" => *merged_top.first().unwrap(),
+                "Last" => *merged_top.last().unwrap(),
+                "Count" => count,
+            };
+            document
+                .objects
+                .insert(root_id, Object::Dictionary(outlines_dict));
+        }
+    }
+
     // Build a new "Pages" with updated fields
     if let Ok(dictionary) = pages_object.1.as_dict() {
         let mut dictionary = dictionary.clone();
@@ -250,7 +700,14 @@ pub fn insert(destination: &Path, after_pages: &Vec<u32>, source: &Path) {
     if let Ok(dictionary) = catalog_object.1.as_dict() {
         let mut dictionary = dictionary.clone();
         dictionary.set("Pages", pages_object.0);
-        dictionary.remove(b"Outlines"); // Outlines not supported in merged PDFs
+        match outlines_object {
+            Some(root_id) if !merged_top.is_empty() => {
+                dictionary.set("Outlines", root_id);
+            }
+            _ => {
+                dictionary.remove(b"Outlines");
+            }
+        }
 
         document
             .objects
@@ -266,8 +723,230 @@ pub fn insert(destination: &Path, after_pages: &Vec<u32>, source: &Path) {
     document.renumber_objects();
     document.compress();
 
-    // Save the merged PDF
-    document.save(destination).unwrap();
+    // Encode the merged PDF
+    let mut buffer = Vec::new();
+    document.save_to(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Insert a pdf `source` in possible multiple places in another pdf `destination`.
+///
+/// # NOTE:
+/// This overwrites the pdf `destination`.
+///
+/// # Errors
+/// Returns [`MergeError::Pdf`] if `source` or `destination` is not a pdf,
+/// [`MergeError::PagesRootNotFound`] or [`MergeError::CatalogRootNotFound`] if either pdf is
+/// missing the corresponding root object, and [`MergeError::Io`] if either file cannot be read
+/// or the merged pdf cannot be saved.
+pub fn insert(destination: &Path, after_pages: &Vec<u32>, source: &Path) -> Result<(), MergeError> {
+    let dst_bytes = std::fs::read(destination)?;
+    let src_bytes = std::fs::read(source)?;
+    let merged = insert_bytes(&dst_bytes, after_pages, &src_bytes)?;
+    std::fs::write(destination, merged)?;
+    Ok(())
+}
+
+/// One entry in an [`assemble`] plan: a pdf plus which of its pages to pull, in what order.
+pub struct PageSource<'a> {
+    /// The pdf bytes to pull pages from.
+    pub document: &'a [u8],
+    /// 0-based page indices to include, in the order they should appear in the assembled
+    /// document. Pages may be repeated or reordered. `None` means every page, in order.
+    pub pages: Option<Vec<u32>>,
+}
+
+/// Assemble pages from any number of pdfs into a single [`Document`], in the order given by
+/// `plan`. This generalizes [`insert`] (which only interleaves one `source` into one
+/// `destination` at whole-pdf granularity) to concatenating, reordering, or selecting individual
+/// pages across many documents in one pass.
+///
+/// Bookmarks are not carried over; see [`insert`] if you need the two-document outline merge.
+///
+/// # Errors
+/// Returns [`MergeError::Pdf`] if any source is not a pdf, [`MergeError::PageIndexOutOfRange`]
+/// if a [`PageSource`] selects a page its document doesn't have, and
+/// [`MergeError::PagesRootNotFound`] or [`MergeError::CatalogRootNotFound`] if any source is
+/// missing the corresponding root object.
+pub fn assemble(plan: &[PageSource]) -> Result<Document, MergeError> {
+    let mut documents = plan
+        .iter()
+        .map(|source| Document::load_mem(source.document))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Resolve each source's selection to a concrete, ordered list of its own page numbers.
+    let selections: Vec<Vec<u32>> = plan
+        .iter()
+        .zip(&documents)
+        .map(|(source, document)| {
+            source
+                .pages
+                .clone()
+                .unwrap_or_else(|| (0..document.get_pages().len() as u32).collect())
+        })
+        .collect();
+
+    let num_pages: u32 = selections.iter().map(|pages| pages.len() as u32).sum();
+
+    let mut document = Document::with_version("1.5");
+    let mut max_id = num_pages + 1;
+
+    let mut documents_pages: BTreeMap<ObjectId, Object> = BTreeMap::new();
+    let mut documents_objects: BTreeMap<ObjectId, Object> = BTreeMap::new();
+    let mut result_page_index: u32 = 0;
+
+    // Each source's `/Outlines` root, if any. Captured before that source's `objects` is moved
+    // into `documents_objects` below; used further down to drop its outline tree by reachability
+    // rather than by `/Type` tag, since outline item dicts usually omit `/Type`.
+    let mut outlines_roots: Vec<ObjectId> = Vec::new();
+
+    for (source_document, selection) in documents.iter_mut().zip(&selections) {
+        // Renumber this source's objects so its ids don't collide with any other source's.
+        source_document.renumber_objects_with(max_id);
+        max_id = source_document.max_id;
+
+        let page_ids: Vec<ObjectId> = source_document.get_pages().into_values().collect();
+
+        for &page_number in selection {
+            let object_id = *page_ids
+                .get(page_number as usize)
+                .ok_or(MergeError::PageIndexOutOfRange(page_number))?;
+            result_page_index += 1;
+            documents_pages.insert(
+                (result_page_index, object_id.1),
+                source_document.get_object(object_id)?.to_owned(),
+            );
+        }
+
+        outlines_roots.extend(
+            source_document
+                .catalog()
+                .ok()
+                .and_then(|catalog| catalog.get(b"Outlines").ok())
+                .and_then(|object| object.as_reference().ok()),
+        );
+
+        documents_objects.extend(std::mem::take(&mut source_document.objects));
+    }
+
+    // Outline trees aren't spliced across an arbitrary number of sources, so every source's
+    // outline tree is dropped here; walk reachability from each root rather than relying on
+    // `/Type`, since individual outline item dicts usually omit it.
+    let excluded_outline_ids: BTreeSet<ObjectId> = outlines_roots
+        .iter()
+        .copied()
+        .chain(
+            outlines_roots
+                .iter()
+                .flat_map(|root| outline_descendants(&documents_objects, *root)),
+        )
+        .collect();
+
+    // Catalog and Pages are mandatory
+    let mut catalog_object: Option<(ObjectId, Object)> = None;
+    let mut pages_object: Option<(ObjectId, Object)> = None;
+
+    // Process all objects except "Page" type, same as `insert_bytes`.
+    for (object_id, object) in documents_objects.iter() {
+        if excluded_outline_ids.contains(object_id) {
+            continue;
+        }
+
+        match object.type_name().unwrap_or("") {
+            "Catalog" => {
+                catalog_object = Some((
+                    if let Some((id, _)) = catalog_object {
+                        id
+                    } else {
+                        *object_id
+                    },
+                    object.clone(),
+                ));
+            }
+            "Pages" => {
+                if let Ok(dictionary) = object.as_dict() {
+                    let mut dictionary = dictionary.clone();
+                    if let Some((_, ref object)) = pages_object {
+                        if let Ok(old_dictionary) = object.as_dict() {
+                            dictionary.extend(old_dictionary);
+                        }
+                    }
+
+                    pages_object = Some((
+                        if let Some((id, _)) = pages_object {
+                            id
+                        } else {
+                            *object_id
+                        },
+                        Object::Dictionary(dictionary),
+                    ));
+                }
+            }
+            "Page" => {}
+            _ => {
+                document.objects.insert(*object_id, object.clone());
+            }
+        }
+    }
+
+    if pages_object.is_none() {
+        return Err(MergeError::PagesRootNotFound);
+    }
+
+    // Iter over all "Page" and collect with the parent "Pages" created before
+    for (object_id, object) in documents_pages.iter() {
+        if let Ok(dictionary) = object.as_dict() {
+            let mut dictionary = dictionary.clone();
+            dictionary.set("Parent", pages_object.as_ref().unwrap().0);
+
+            document
+                .objects
+                .insert(*object_id, Object::Dictionary(dictionary));
+        }
+    }
+
+    if catalog_object.is_none() {
+        return Err(MergeError::CatalogRootNotFound);
+    }
+
+    let catalog_object = catalog_object.unwrap();
+    let pages_object = pages_object.unwrap();
+
+    // Build a new "Pages" with updated fields
+    if let Ok(dictionary) = pages_object.1.as_dict() {
+        let mut dictionary = dictionary.clone();
+        dictionary.set("Count", documents_pages.len() as u32);
+        dictionary.set(
+            "Kids",
+            documents_pages
+                .keys()
+                .map(|object_id| Object::Reference(*object_id))
+                .collect::<Vec<_>>(),
+        );
+
+        document
+            .objects
+            .insert(pages_object.0, Object::Dictionary(dictionary));
+    }
+
+    // Build a new "Catalog" with updated fields
+    if let Ok(dictionary) = catalog_object.1.as_dict() {
+        let mut dictionary = dictionary.clone();
+        dictionary.set("Pages", pages_object.0);
+        dictionary.remove(b"Outlines");
+
+        document
+            .objects
+            .insert(catalog_object.0, Object::Dictionary(dictionary));
+    }
+
+    document.trailer.set("Root", catalog_object.0);
+    document.max_id = document.objects.len() as u32;
+
+    document.renumber_objects();
+    document.compress();
+
+    Ok(document)
 }
 
 #[cfg(test)]
@@ -275,21 +954,167 @@ mod tests {
     use std::fs;
     use std::path::Path;
 
+    use lopdf::{dictionary, Document, Object, ObjectId, StringFormat};
+
+    /// Build a minimal in-memory pdf with `page_count` blank pages and a top-level outline
+    /// (bookmark) tree, one item per `(title, dest_page)` entry in `bookmarks`, each pointing at
+    /// the dst/src page with that 0-based index.
+    fn build_pdf_with_outline(page_count: u32, bookmarks: &[(&str, u32)]) -> Vec<u8> {
+        let mut document = Document::with_version("1.5");
+
+        let pages_id = document.new_object_id();
+        let page_ids: Vec<ObjectId> = (0..page_count)
+            .map(|_| {
+                document.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "MediaBox" => vec![
+                        Object::Integer(0),
+                        Object::Integer(0),
+                        Object::Integer(612),
+                        Object::Integer(792),
+                    ],
+                })
+            })
+            .collect();
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => page_count as i64,
+                "Kids" => page_ids.iter().map(|&id| Object::Reference(id)).collect::<Vec<_>>(),
+            }),
+        );
+
+        let outlines_id = document.new_object_id();
+        let item_ids: Vec<ObjectId> = bookmarks
+            .iter()
+            .map(|(title, dest_page)| {
+                document.add_object(dictionary! {
+                    "Title" => Object::String(title.as_bytes().to_vec(), StringFormat::Literal),
+                    "Parent" => outlines_id,
+                    "Dest" => vec![
+                        Object::Reference(page_ids[*dest_page as usize]),
+                        Object::Name(b"Fit".to_vec()),
+                    ],
+                })
+            })
+            .collect();
+        for (index, &item_id) in item_ids.iter().enumerate() {
+            let dict = document.get_object_mut(item_id).unwrap().as_dict_mut().unwrap();
+            if index > 0 {
+                dict.set("Prev", item_ids[index - 1]);
+            }
+            if index + 1 < item_ids.len() {
+                dict.set("Next", item_ids[index + 1]);
+            }
+        }
+        document.objects.insert(
+            outlines_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Outlines",
+                "First" => *item_ids.first().unwrap(),
+                "Last" => *item_ids.last().unwrap(),
+                "Count" => item_ids.len() as i64,
+            }),
+        );
+
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "Outlines" => outlines_id,
+        });
+        document.trailer.set("Root", catalog_id);
+        document.max_id = document.objects.len() as u32;
+
+        let mut buffer = Vec::new();
+        document.save_to(&mut buffer).unwrap();
+        buffer
+    }
+
+    /// Walk a merged document's top-level outline chain via `/Root` -> `/Outlines` -> `/First`
+    /// -> `/Next`, returning each item's title and the 0-based page index its `/Dest` now
+    /// resolves to.
+    fn outline_chain(document: &Document) -> Vec<(String, u32)> {
+        let pages = document.get_pages();
+        let outlines_id = document
+            .catalog()
+            .unwrap()
+            .get(b"Outlines")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+
+        let mut chain = Vec::new();
+        let mut current = document
+            .get_object(outlines_id)
+            .unwrap()
+            .as_dict()
+            .unwrap()
+            .get(b"First")
+            .ok()
+            .and_then(|object| object.as_reference().ok());
+
+        while let Some(item_id) = current {
+            let dict = document.get_object(item_id).unwrap().as_dict().unwrap();
+            let title = match dict.get(b"Title").unwrap() {
+                Object::String(bytes, _) => String::from_utf8(bytes.clone()).unwrap(),
+                other => panic!("expected a /Title string, got {other:?}"),
+            };
+            let dest_page_id = dict.get(b"Dest").unwrap().as_array().unwrap()[0]
+                .as_reference()
+                .unwrap();
+            let page_number = pages
+                .iter()
+                .find_map(|(&number, &page_id)| (page_id == dest_page_id).then_some(number - 1))
+                .unwrap();
+            chain.push((title, page_number));
+
+            current = dict
+                .get(b"Next")
+                .ok()
+                .and_then(|object| object.as_reference().ok());
+        }
+
+        chain
+    }
+
+    #[test]
+    fn insert_bytes_splices_and_remaps_outlines() {
+        // dst has a bookmark on each of its two pages; src has one bookmark on its only page.
+        let dst = build_pdf_with_outline(2, &[("Dst One", 0), ("Dst Two", 1)]);
+        let src = build_pdf_with_outline(1, &[("Src One", 0)]);
+
+        // Insert src after dst's first page (`after_pages` counts the dst pages that precede the
+        // insertion), i.e. between "Dst One" and "Dst Two".
+        let merged = super::insert_bytes(&dst, &[1], &src).unwrap();
+        let document = Document::load_mem(&merged).unwrap();
+
+        let chain = outline_chain(&document);
+        let titles: Vec<&str> = chain.iter().map(|(title, _)| title.as_str()).collect();
+        assert_eq!(titles, vec!["Dst One", "Src One", "Dst Two"]);
+
+        // Every bookmark's `/Dest` was remapped to land on the page it originally pointed to.
+        assert_eq!(chain[0].1, 0); // "Dst One" still targets dst's first page, now page 0.
+        assert_eq!(chain[1].1, 1); // "Src One" now targets the spliced-in src page, page 1.
+        assert_eq!(chain[2].1, 2); // "Dst Two" still targets dst's second page, now page 2.
+    }
+
     #[test]
     fn merge() -> Result<(), std::io::Error> {
         fs::copy("./Test1.pdf", "./Test1_tmp.pdf")?;
         let a = Path::new("./Test1_tmp.pdf");
         let b = Path::new("./Test2.pdf");
 
-        let length_a = super::pdf_get_size(a);
+        let length_a = super::pdf_get_size(a).unwrap();
         assert_eq!(length_a, 9);
-        let length_b = super::pdf_get_size(b);
+        let length_b = super::pdf_get_size(b).unwrap();
         assert_eq!(length_b, 2);
 
         let target_indexes: Vec<u32> = vec![0, 1, 2, 4, length_a as u32 - 1];
-        super::insert(&a, &target_indexes, &b);
+        super::insert(&a, &target_indexes, &b).unwrap();
         assert_eq!(
-            super::pdf_get_size(a),
+            super::pdf_get_size(a).unwrap(),
             length_a + target_indexes.len() * length_b
         );
 
@@ -302,11 +1127,102 @@ mod tests {
         fs::copy("./Test1.pdf", "./Test1_tmp2.pdf")?;
         let a = Path::new("./Test1_tmp2.pdf");
 
-        let size = super::pdf_get_size(a);
+        let size = super::pdf_get_size(a).unwrap();
         assert_eq!(size % 2, 1);
-        super::make_page_count_even(a);
-        assert_eq!(super::pdf_get_size(a) % 2, 0);
-        assert_eq!(super::pdf_get_size(a), size + 1);
+        super::make_page_count_even(a).unwrap();
+        assert_eq!(super::pdf_get_size(a).unwrap() % 2, 0);
+        assert_eq!(super::pdf_get_size(a).unwrap(), size + 1);
+
+        fs::remove_file(a)?;
+        return Ok(());
+    }
+
+    #[test]
+    fn merge_bytes() -> Result<(), std::io::Error> {
+        let dst = fs::read("./Test1.pdf")?;
+        let src = fs::read("./Test2.pdf")?;
+
+        let length_a = super::pdf_get_size_bytes(&dst).unwrap();
+        assert_eq!(length_a, 9);
+        let length_b = super::pdf_get_size_bytes(&src).unwrap();
+        assert_eq!(length_b, 2);
+
+        let target_indexes: Vec<u32> = vec![0, 1, 2, 4, length_a as u32 - 1];
+        let merged = super::insert_bytes(&dst, &target_indexes, &src).unwrap();
+        assert_eq!(
+            super::pdf_get_size_bytes(&merged).unwrap(),
+            length_a + target_indexes.len() * length_b
+        );
+
+        return Ok(());
+    }
+
+    #[test]
+    fn make_even_bytes() -> Result<(), std::io::Error> {
+        let original = fs::read("./Test1.pdf")?;
+
+        let size = super::pdf_get_size_bytes(&original).unwrap();
+        assert_eq!(size % 2, 1);
+        let updated = super::make_page_count_even_bytes(&original).unwrap();
+        assert_eq!(super::pdf_get_size_bytes(&updated).unwrap() % 2, 0);
+        assert_eq!(super::pdf_get_size_bytes(&updated).unwrap(), size + 1);
+
+        return Ok(());
+    }
+
+    #[test]
+    fn assemble_concatenates_and_selects_pages() -> Result<(), std::io::Error> {
+        let a = fs::read("./Test1.pdf")?;
+        let b = fs::read("./Test2.pdf")?;
+
+        let length_a = super::pdf_get_size_bytes(&a).unwrap();
+        let length_b = super::pdf_get_size_bytes(&b).unwrap();
+
+        let plan = vec![
+            super::PageSource {
+                document: &a,
+                pages: None,
+            },
+            // Reorder b's pages, dropping none of them.
+            super::PageSource {
+                document: &b,
+                pages: Some((0..length_b as u32).rev().collect()),
+            },
+        ];
+        let assembled = super::assemble(&plan).unwrap();
+        assert_eq!(assembled.get_pages().len(), length_a + length_b);
+
+        return Ok(());
+    }
+
+    #[test]
+    fn assemble_rejects_out_of_range_page() -> Result<(), std::io::Error> {
+        let a = fs::read("./Test1.pdf")?;
+        let plan = vec![super::PageSource {
+            document: &a,
+            pages: Some(vec![999]),
+        }];
+
+        assert!(matches!(
+            super::assemble(&plan),
+            Err(super::MergeError::PageIndexOutOfRange(999))
+        ));
+
+        return Ok(());
+    }
+
+    #[test]
+    fn stamp_page_numbers() -> Result<(), std::io::Error> {
+        fs::copy("./Test1.pdf", "./Test1_tmp3.pdf")?;
+        let a = Path::new("./Test1_tmp3.pdf");
+        let original_len = fs::metadata(a)?.len();
+
+        let size = super::pdf_get_size(a).unwrap();
+        super::stamp_page_numbers(a, super::StampOptions::default()).unwrap();
+
+        // Stamping adds a content stream and a font to every page, but doesn't add or remove pages.
+        assert_eq!(super::pdf_get_size(a).unwrap(), size);
+        assert!(fs::metadata(a)?.len() > original_len);
 
         fs::remove_file(a)?;
         return Ok(());